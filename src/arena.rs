@@ -1,10 +1,33 @@
+use std::collections::TryReserveError;
+use std::rc::Rc;
 use std::vec::Vec;
 
+/// A reference to a slot in an `Arena`. Carries the slot's generation so a
+/// handle into a since-removed-and-reused slot is distinguishable from a
+/// live one: `get`/`get_mut` return `None` rather than aliasing it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct Handle {
+    index: usize,
+    generation: u32
+}
+
+/// Each slot's node is kept behind an `Rc` so cloning the arena only bumps
+/// refcounts; `get_mut` copies a node in place the first time it's shared.
 pub(crate) struct Arena<Node> {
-    nodes: Vec<Option<Node>>,
+    nodes: Vec<(u32, Option<Rc<Node>>)>,
     available: Vec<usize>
 }
 
+// By hand instead of `#[derive(Clone)]`, which would require `Node: Clone`.
+impl<Node> Clone for Arena<Node> {
+    fn clone(&self) -> Self {
+        return Arena{
+            nodes: self.nodes.clone(),
+            available: self.available.clone()
+        };
+    }
+}
+
 impl<'a, Node> Arena<Node> {
     pub fn new() -> Arena<Node> {
         return Arena{
@@ -13,40 +36,53 @@ impl<'a, Node> Arena<Node> {
         };
     }
 
-    pub fn alloc(&mut self, new_node: Node) -> usize {
+    // Reused slots are written in place rather than through `Vec::insert`,
+    // which would shift later elements and invalidate every handle already
+    // handed out.
+    pub fn try_alloc(&mut self, new_node: Node) -> Result<Handle, TryReserveError> {
         if let Some(index) = self.available.pop() {
-            self.nodes.insert(index, Some(new_node));
-            return index;
+            let slot = &mut self.nodes[index];
+            slot.1 = Some(Rc::new(new_node));
+            return Ok(Handle{ index: index, generation: slot.0 });
         }
 
-        self.nodes.push(Some(new_node));
-        return self.nodes.len() - 1;
+        self.nodes.try_reserve(1)?;
+        self.nodes.push((0, Some(Rc::new(new_node))));
+        return Ok(Handle{ index: self.nodes.len() - 1, generation: 0 });
     }
 
-    pub fn remove(&mut self, index: usize) -> bool {
-        let to_erase = &mut self.nodes[index];
-        if to_erase.is_some() {
-            *to_erase = None;
-            self.available.push(index);
-            return true;
+    pub fn remove(&mut self, handle: Handle) -> bool {
+        if let Some((generation, slot)) = self.nodes.get_mut(handle.index) {
+            if *generation == handle.generation && slot.is_some() {
+                *slot = None;
+                *generation = generation.wrapping_add(1);
+                self.available.push(handle.index);
+                return true;
+            }
         }
 
         return false;
     }
 
-    pub fn view(&'a self, index: usize) -> &'a Node {
-        if let Some(node) = &self.nodes[index] {
-            return node;
-        } else {
-            panic!("Requested access to a dead node: {}", index);
+    pub fn get(&'a self, handle: Handle) -> Option<&'a Node> {
+        match self.nodes.get(handle.index) {
+            Some((generation, Some(node))) if *generation == handle.generation => Some(node),
+            _ => None
         }
     }
+}
 
-    pub fn modify(&'a mut self, index: usize) -> &'a mut Node {
-        if let Some(node) = &mut self.nodes[index] {
-            return node;
-        } else {
-            panic!("Requested mutable access to a dead node: {}", index);
+impl<'a, Node: Clone> Arena<Node> {
+    /// Like `get`, but the returned reference is mutable. If this slot's
+    /// node is currently shared with another snapshot (strong count > 1),
+    /// it is cloned first via `Rc::make_mut`, so the mutation only ever
+    /// affects this arena's copy.
+    pub fn get_mut(&'a mut self, handle: Handle) -> Option<&'a mut Node> {
+        match self.nodes.get_mut(handle.index) {
+            Some((generation, Some(node))) if *generation == handle.generation => {
+                Some(Rc::make_mut(node))
+            },
+            _ => None
         }
     }
 }
@@ -0,0 +1,9 @@
+/// Lets a payload describe children to materialize lazily and whether it
+/// matches an ad hoc search term.
+pub trait TreeItem<Key, Payload> {
+    /// Keyed children to insert under this item, via `BinarySearchTree::expand`.
+    fn children(&self) -> Vec<(Key, Payload)>;
+
+    /// Whether this item should be kept by `BinarySearchTree::filtered_iter`.
+    fn filter(&self, needle: &str) -> bool;
+}
@@ -3,23 +3,31 @@ use std::cmp::PartialOrd;
 
 mod arena;
 use arena::Arena;
+use arena::Handle;
+
+mod lazy;
+pub use lazy::TreeItem;
 
 type Storage<Key, Payload> = Arena<Node<Key, Payload>>;
 
+#[derive(Clone)]
 pub struct Content<Key, Payload> {
     key: Key,
     payload: Payload
 }
 
+#[derive(Clone)]
 struct Node<Key: PartialOrd, Payload> {
     content: Content<Key, Payload>,
-    parent: Option<usize>,
-    left_child: Option<usize>,
-    right_child : Option<usize>
+    parent: Option<Handle>,
+    left_child: Option<Handle>,
+    right_child : Option<Handle>,
+    subtree_size: usize,
+    height: i32
 }
 
 enum TraverseTowards {
-    Next(usize),
+    Next(Handle),
     InsertLeft,
     InsertRight,
     Current
@@ -52,77 +60,187 @@ impl<Key: PartialOrd, Payload> Node<Key, Payload> {
         return TraverseTowards::Current;
     }
 
+    fn view<'a>(storage: &'a Storage<Key, Payload>, handle: Handle) -> &'a Node<Key, Payload> {
+        return storage.get(handle).expect("node handle should still be valid");
+    }
+
     fn fall_min(
         storage: &Storage<Key, Payload>,
-        mut current_node_index: usize
-    ) -> usize {
+        mut current_handle: Handle
+    ) -> Handle {
         loop {
-            let current_node = storage.view(current_node_index);
+            let current_node = Node::view(storage, current_handle);
             if let Some(left) = current_node.left_child {
-                current_node_index = left;
+                current_handle = left;
             } else {
-                return current_node_index;
+                return current_handle;
             }
         }
     }
 
     fn fall_max(
         storage: &Storage<Key, Payload>,
-        mut current_node_index: usize
-    ) -> usize {
+        mut current_handle: Handle
+    ) -> Handle {
         loop {
-            let current_node = storage.view(current_node_index);
+            let current_node = Node::view(storage, current_handle);
             if let Some(right) = current_node.right_child {
-                current_node_index = right;
+                current_handle = right;
+            } else {
+                return current_handle;
+            }
+        }
+    }
+
+    // Finds the node holding the smallest key satisfying `bound` as a
+    // lower bound, or `None` if nothing in the subtree does.
+    fn seek_lower_bound<Query>(
+        storage: &Storage<Key, Payload>,
+        mut current: Option<Handle>,
+        bound: std::ops::Bound<&Query>
+    ) -> Option<Handle>
+    where
+        Key: PartialOrd<Query>,
+        Query: PartialOrd<Key> + ?Sized
+    {
+        let mut candidate = None;
+        while let Some(handle) = current {
+            let node = Node::view(storage, handle);
+            let satisfies = match bound {
+                std::ops::Bound::Included(q) => !(node.content.key < *q),
+                std::ops::Bound::Excluded(q) => *q < node.content.key,
+                std::ops::Bound::Unbounded => true
+            };
+
+            if satisfies {
+                candidate = Some(handle);
+                current = node.left_child;
             } else {
-                return current_node_index;
+                current = node.right_child;
             }
         }
+
+        return candidate;
+    }
+
+    // Finds the node holding the largest key satisfying `bound` as an
+    // upper bound, or `None` if nothing in the subtree does.
+    fn seek_upper_bound<Query>(
+        storage: &Storage<Key, Payload>,
+        mut current: Option<Handle>,
+        bound: std::ops::Bound<&Query>
+    ) -> Option<Handle>
+    where
+        Key: PartialOrd<Query>,
+        Query: PartialOrd<Key> + ?Sized
+    {
+        let mut candidate = None;
+        while let Some(handle) = current {
+            let node = Node::view(storage, handle);
+            let satisfies = match bound {
+                std::ops::Bound::Included(q) => !(*q < node.content.key),
+                std::ops::Bound::Excluded(q) => node.content.key < *q,
+                std::ops::Bound::Unbounded => true
+            };
+
+            if satisfies {
+                candidate = Some(handle);
+                current = node.right_child;
+            } else {
+                current = node.left_child;
+            }
+        }
+
+        return candidate;
     }
 
     fn climb(
         storage: &Storage<Key, Payload>,
-        mut from_node_index: usize
-    ) -> Option<usize> {
+        mut from_handle: Handle
+    ) -> Option<Handle> {
         loop {
-            let from_node = storage.view(from_node_index);
-            let check_to_node_index = from_node.parent;
-            if let Some(to_node_index) = check_to_node_index {
-                let to_node = storage.view(to_node_index);
-                if let Some(right_child_index) = to_node.right_child {
-                    if right_child_index == from_node_index {
-                        from_node_index = to_node_index;
+            let from_node = Node::view(storage, from_handle);
+            let check_to_handle = from_node.parent;
+            if let Some(to_handle) = check_to_handle {
+                let to_node = Node::view(storage, to_handle);
+                if let Some(right_child_handle) = to_node.right_child {
+                    if right_child_handle == from_handle {
+                        from_handle = to_handle;
                         continue;
                     }
                 }
 
-                return Some(to_node_index);
+                return Some(to_handle);
             }
 
             return None;
         }
     }
 
-    fn new(
+    fn try_new(
         storage: &mut Storage<Key, Payload>,
         key: Key,
         payload: Payload,
-        parent: Option<usize>,
-    ) -> usize {
-        return storage.alloc(
+        parent: Option<Handle>,
+    ) -> Result<Handle, std::collections::TryReserveError> {
+        return storage.try_alloc(
             Node{
                 content: Content{key: key, payload: payload},
                 parent: parent,
                 left_child: None,
-                right_child: None
+                right_child: None,
+                subtree_size: 1,
+                height: 1
             }
         );
     }
+
+    fn size_of(storage: &Storage<Key, Payload>, node: Option<Handle>) -> usize {
+        match node {
+            Some(handle) => Node::view(storage, handle).subtree_size,
+            None => 0
+        }
+    }
+
+    fn height_of(storage: &Storage<Key, Payload>, node: Option<Handle>) -> i32 {
+        match node {
+            Some(handle) => Node::view(storage, handle).height,
+            None => 0
+        }
+    }
+
+}
+
+// Split out because these (and `modify`) are the only operations that
+// mutate a node in place, so they're the only ones needing `Clone` to
+// back `Arena::get_mut`'s `Rc::make_mut`.
+impl<Key: PartialOrd + Clone, Payload: Clone> Node<Key, Payload> {
+    fn modify<'a>(storage: &'a mut Storage<Key, Payload>, handle: Handle) -> &'a mut Node<Key, Payload> {
+        return storage.get_mut(handle).expect("node handle should still be valid");
+    }
+
+    fn recompute_subtree_size(storage: &mut Storage<Key, Payload>, handle: Handle) {
+        let node = Node::view(storage, handle);
+        let size = 1
+            + Node::size_of(storage, node.left_child)
+            + Node::size_of(storage, node.right_child);
+        Node::modify(storage, handle).subtree_size = size;
+    }
+
+    fn recompute_height(storage: &mut Storage<Key, Payload>, handle: Handle) {
+        let node = Node::view(storage, handle);
+        let height = 1 + std::cmp::max(
+            Node::height_of(storage, node.left_child),
+            Node::height_of(storage, node.right_child)
+        );
+        Node::modify(storage, handle).height = height;
+    }
 }
 
 pub struct BinarySearchTree<Key: PartialOrd, Payload> {
     storage: Storage<Key, Payload>,
-    root: Option<usize>,
+    root: Option<Handle>,
+    balanced: bool,
 }
 
 impl<'g, Key: PartialOrd + Display, Payload: Display> BinarySearchTree<Key, Payload> {
@@ -131,54 +249,207 @@ impl<'g, Key: PartialOrd + Display, Payload: Display> BinarySearchTree<Key, Payl
         return BinarySearchTree{
             storage: Arena::new(),
             root: None,
+            balanced: false,
         };
     }
 
+    /// Like `new`, but the tree will keep itself height-balanced with AVL
+    /// rotations on every `insert`/`remove`, trading a little extra work
+    /// per mutation for an O(log n) guarantee on `traverse_towards`-based
+    /// lookups even when keys arrive in sorted order.
+    pub fn new_balanced() -> BinarySearchTree<Key, Payload> {
+        return BinarySearchTree{
+            storage: Arena::new(),
+            root: None,
+            balanced: true,
+        };
+    }
+
+    /// Returns the 0-based rank of `query` among the keys currently in the
+    /// tree, i.e. how many keys compare less than it, or `None` if `query`
+    /// is not present.
+    pub fn rank<Query>(&self, query: &Query) -> Option<usize>
+    where
+        Key: std::borrow::Borrow<Query> + PartialOrd<Query>,
+        Query: PartialOrd<Key> + ?Sized
+    {
+        let mut current = self.root;
+        let mut acc = 0usize;
+        while let Some(handle) = current {
+            let node = Node::view(&self.storage, handle);
+            if node.content.key < *query {
+                acc += Node::size_of(&self.storage, node.left_child) + 1;
+                current = node.right_child;
+            } else if *query < node.content.key {
+                current = node.left_child;
+            } else {
+                return Some(acc + Node::size_of(&self.storage, node.left_child));
+            }
+        }
+
+        return None;
+    }
+
+    /// Returns the k-th smallest element (0-based) in the tree, or `None`
+    /// if `k` is out of range.
+    pub fn select(&self, mut k: usize) -> Option<&Content<Key, Payload>> {
+        let mut current = self.root;
+        while let Some(handle) = current {
+            let node = Node::view(&self.storage, handle);
+            let left_size = Node::size_of(&self.storage, node.left_child);
+            if k < left_size {
+                current = node.left_child;
+            } else if k == left_size {
+                return Some(&node.content);
+            } else {
+                k -= left_size + 1;
+                current = node.right_child;
+            }
+        }
+
+        return None;
+    }
+
+    pub fn iter(&'g self) -> BSTIterator<'g, Key, Payload> {
+        if let Some(root) = self.root {
+            return BSTIterator{ storage: &self.storage, node: Some(Node::fall_min(&self.storage, root)), end: None };
+        } else {
+            return BSTIterator{ storage: &self.storage, node: None, end: None };
+        }
+    }
+
+    /// Returns an iterator over the keys (and their payloads) that fall
+    /// within `bounds`, in ascending order, e.g. `tree.range(5..10)` or
+    /// `tree.range(..="z")`.
+    pub fn range<Query, R>(&'g self, bounds: R) -> BSTIterator<'g, Key, Payload>
+    where
+        Key: std::borrow::Borrow<Query> + PartialOrd<Query>,
+        Query: PartialOrd<Key> + ?Sized,
+        R: std::ops::RangeBounds<Query>
+    {
+        let start = Node::seek_lower_bound(&self.storage, self.root, bounds.start_bound());
+        let end = Node::seek_upper_bound(&self.storage, self.root, bounds.end_bound());
+        if let (Some(start_handle), Some(end_handle)) = (start, end) {
+            let start_key = &Node::view(&self.storage, start_handle).content.key;
+            let end_key = &Node::view(&self.storage, end_handle).content.key;
+            if *start_key <= *end_key {
+                return BSTIterator{ storage: &self.storage, node: Some(start_handle), end: Some(end_handle) };
+            }
+        }
+
+        return BSTIterator{ storage: &self.storage, node: None, end: None };
+    }
+
+    /// Returns the height of the tree (0 if it is empty).
+    pub fn height(&self) -> i32 {
+        return Node::height_of(&self.storage, self.root);
+    }
+
+    /// Returns an independent, immutable-from-here-on version of the tree
+    /// that will not observe any later mutation made through `self`. O(n) in
+    /// the tree's capacity to clone the index spine, not O(1); cheaper than
+    /// a deep clone since node content is shared until mutated.
+    pub fn snapshot(&self) -> BinarySearchTree<Key, Payload> {
+        return BinarySearchTree{
+            storage: self.storage.clone(),
+            root: self.root,
+            balanced: self.balanced
+        };
+    }
+
+    pub fn print_root(&self) {
+        if let Some(root) = self.root {
+            let root_node = Node::view(&self.storage, root);
+            println!(
+                "root: key: {} | value: {}",
+                &root_node.content.key,
+                &root_node.content.payload
+            );
+        } else {
+            println!("There is no root!");
+        }
+    }
+
+    fn find_handle<Query>(&self, query: &Query) -> Option<Handle>
+    where
+        Key: std::borrow::Borrow<Query> + PartialOrd<Query>,
+        Query: PartialOrd<Key> + ?Sized
+    {
+        let mut node = self.root?;
+        loop {
+            match Node::view(&self.storage, node).traverse_towards(query) {
+                TraverseTowards::Next(n) => node = n,
+                TraverseTowards::Current => return Some(node),
+                TraverseTowards::InsertLeft | TraverseTowards::InsertRight => return None
+            }
+        }
+    }
+}
+
+// `insert`/`remove` and everything they call down into (`retrace`,
+// `rebalance_at`, the rotations, ...) mutate nodes in place via
+// `Node::modify`, which needs `Key`/`Payload: Clone` to back
+// `Arena::get_mut`'s copy-on-write. The read-only methods above don't
+// touch `Node::modify` and so don't need that bound.
+impl<'g, Key: PartialOrd + Display + Clone, Payload: Display + Clone> BinarySearchTree<Key, Payload> {
+
     pub fn insert(&'g mut self, key: Key, payload: Payload) -> InsertionResult<'g, Key, Payload> {
+        return self.try_insert(key, payload).expect("allocation failed");
+    }
 
+    /// Like `insert`, but returns an error when the node arena cannot grow,
+    /// where `insert` panics. Allocating the node's own `Rc` is not
+    /// fallible and still aborts on out-of-memory.
+    pub fn try_insert(
+        &'g mut self,
+        key: Key,
+        payload: Payload
+    ) -> Result<InsertionResult<'g, Key, Payload>, std::collections::TryReserveError> {
         if let Some(mut node) = self.root {
             loop {
-                let next = self.storage.view(node).traverse_towards(&key);
+                let next = Node::view(&self.storage, node).traverse_towards(&key);
                 match next {
                     TraverseTowards::Next(n) => {
                         node = n;
                     },
                     TraverseTowards::InsertLeft => {
                         let new_left_child =
-                            Some(Node::new(&mut self.storage, key, payload, Some(node)));
+                            Some(Node::try_new(&mut self.storage, key, payload, Some(node))?);
 
-                        self.storage.modify(node).left_child = new_left_child;
+                        Node::modify(&mut self.storage, node).left_child = new_left_child;
+                        self.retrace(node, None);
 
-                        return InsertionResult{
+                        return Ok(InsertionResult{
                             inserted: true,
-                            iterator: BSTIterator{storage: &self.storage, node: new_left_child }
-                        };
+                            iterator: BSTIterator{storage: &self.storage, node: new_left_child, end: None }
+                        });
                     },
                     TraverseTowards::InsertRight => {
                         let new_right_child =
-                            Some(Node::new(&mut self.storage, key, payload, Some(node)));
+                            Some(Node::try_new(&mut self.storage, key, payload, Some(node))?);
 
-                        self.storage.modify(node).right_child = new_right_child;
+                        Node::modify(&mut self.storage, node).right_child = new_right_child;
+                        self.retrace(node, None);
 
-                        return InsertionResult{
+                        return Ok(InsertionResult{
                             inserted: true,
-                            iterator: BSTIterator{ storage: &self.storage, node: new_right_child }
-                        };
+                            iterator: BSTIterator{ storage: &self.storage, node: new_right_child, end: None }
+                        });
                     },
                     TraverseTowards::Current => {
-                        return InsertionResult{
+                        return Ok(InsertionResult{
                             inserted: false,
-                            iterator: BSTIterator{ storage: &self.storage, node: Some(node) }
-                        };
+                            iterator: BSTIterator{ storage: &self.storage, node: Some(node), end: None }
+                        });
                     }
                 }
             }
         } else {
-            self.root = Some(Node::new(&mut self.storage, key, payload, None));
-            return InsertionResult{
+            self.root = Some(Node::try_new(&mut self.storage, key, payload, None)?);
+            return Ok(InsertionResult{
                 inserted: true,
-                iterator: BSTIterator{ storage: &self.storage, node: self.root }
-            };
+                iterator: BSTIterator{ storage: &self.storage, node: self.root, end: None }
+            });
         }
     }
 
@@ -188,7 +459,7 @@ impl<'g, Key: PartialOrd + Display, Payload: Display> BinarySearchTree<Key, Payl
         Query: PartialOrd<Key> + ?Sized {
         if let Some(mut node) = self.root {
             loop {
-                let next = self.storage.view(node).traverse_towards(&query);
+                let next = Node::view(&self.storage, node).traverse_towards(&query);
                 match next {
                     TraverseTowards::Next(n) => {
                         node = n;
@@ -210,34 +481,34 @@ impl<'g, Key: PartialOrd + Display, Payload: Display> BinarySearchTree<Key, Payl
         return false;
     }
 
-    fn remove_node(&mut self, node_index: usize) {
-        let to_remove = self.storage.view(node_index);
+    fn remove_node(&mut self, handle: Handle) {
+        let to_remove = Node::view(&self.storage, handle);
         let check_parent = to_remove.parent;
         let check_left = to_remove.left_child;
         let check_right = to_remove.right_child;
-        self.storage.remove(node_index);
+        self.storage.remove(handle);
 
-        if let Some(parent_index) = check_parent {
-            let new_child = self.rebuild_tree(check_left, check_right, Some(parent_index));
-            let parent_node = self.storage.modify(parent_index);
+        if let Some(parent_handle) = check_parent {
+            let new_child = self.rebuild_tree(check_left, check_right, Some(parent_handle));
+            let parent_node = Node::modify(&mut self.storage, parent_handle);
             if let Some(old_left) = parent_node.left_child {
-                if old_left == node_index {
+                if old_left == handle {
                     parent_node.left_child = new_child;
+                    self.retrace(parent_handle, None);
                     return;
                 }
             }
 
             if let Some(old_right) = parent_node.right_child {
-                if old_right == node_index {
+                if old_right == handle {
                     parent_node.right_child = new_child;
+                    self.retrace(parent_handle, None);
                     return;
                 }
             }
 
             panic!(
-                "Broken tree! Could not find child {} in node {}. left:{:?}, right:{:?}",
-                node_index,
-                parent_index,
+                "Broken tree! Could not find child in parent node. left:{:?}, right:{:?}",
                 parent_node.left_child,
                 parent_node.right_child
             );
@@ -248,25 +519,27 @@ impl<'g, Key: PartialOrd + Display, Payload: Display> BinarySearchTree<Key, Payl
 
     fn rebuild_tree(
         &mut self,
-        check_left: Option<usize>,
-        check_right: Option<usize>,
-        new_parent: Option<usize>
-    ) -> Option<usize> {
+        check_left: Option<Handle>,
+        check_right: Option<Handle>,
+        new_parent: Option<Handle>
+    ) -> Option<Handle> {
         if let Some(left) = check_left {
             // We will let the left node take the place of its parent
-            self.storage.modify(left).parent = new_parent;
+            Node::modify(&mut self.storage, left).parent = new_parent;
             if let Some(right) = check_right {
-                // If there was a right node, then we will move it to be a child
-                // of the max node in the left subtree.
-                let left_max_index = Node::fall_max(&self.storage, left);
-                self.storage.modify(left_max_index).right_child = Some(right);
-                self.storage.modify(right).parent = Some(left_max_index);
+                // Move it under the left subtree's max node; that may rotate
+                // `left` itself, so trust whatever retrace reports as the new top.
+                let left_max_handle = Node::fall_max(&self.storage, left);
+                Node::modify(&mut self.storage, left_max_handle).right_child = Some(right);
+                Node::modify(&mut self.storage, right).parent = Some(left_max_handle);
+                let new_top = self.retrace(left_max_handle, new_parent);
+                return Some(new_top);
             }
 
             return Some(left);
         } else if let Some(right) = check_right {
             // We will let the right node take the place of the parent
-            self.storage.modify(right).parent = new_parent;
+            Node::modify(&mut self.storage, right).parent = new_parent;
             return Some(right);
         } else {
             // If the removed node has no left or right child, then simply
@@ -275,37 +548,170 @@ impl<'g, Key: PartialOrd + Display, Payload: Display> BinarySearchTree<Key, Payl
         }
     }
 
-    pub fn iter(&'g self) -> BSTIterator<'g, Key, Payload> {
-        if let Some(root) = self.root {
-            return BSTIterator{ storage: &self.storage, node: Some(Node::fall_min(&self.storage, root)) };
-        } else {
-            return BSTIterator{ storage: &self.storage, node: None };
+    // Recomputes size/height up from `handle`, rebalancing each ancestor
+    // when in balanced mode. Stops at `boundary_parent` (`None` for the
+    // root) and returns whichever node ends up there, since rotations can
+    // change which node that is.
+    fn retrace(&mut self, mut handle: Handle, boundary_parent: Option<Handle>) -> Handle {
+        loop {
+            Node::recompute_subtree_size(&mut self.storage, handle);
+            Node::recompute_height(&mut self.storage, handle);
+            if self.balanced {
+                handle = self.rebalance_at(handle);
+            }
+
+            let parent = Node::view(&self.storage, handle).parent;
+            if parent == boundary_parent {
+                return handle;
+            }
+
+            handle = parent.expect("boundary_parent was not found while retracing to the root");
         }
     }
 
-    pub fn print_root(&self) {
-        if let Some(root) = self.root {
-            let root_node = self.storage.view(root);
-            println!(
-                "root: key: {} | value: {}",
-                &root_node.content.key,
-                &root_node.content.payload
-            );
+    fn balance_factor(&self, handle: Handle) -> i32 {
+        let node = Node::view(&self.storage, handle);
+        Node::height_of(&self.storage, node.left_child) - Node::height_of(&self.storage, node.right_child)
+    }
+
+    // Restores |balance factor| <= 1 at `handle` and returns whichever node
+    // now sits there. Recurses into the demoted child after rotating,
+    // because `rebuild_tree`'s subtree-splice can overshoot by more than a
+    // single rotation can fix in one step.
+    fn rebalance_at(&mut self, handle: Handle) -> Handle {
+        let bf = self.balance_factor(handle);
+        if bf >= 2 {
+            let left = Node::view(&self.storage, handle).left_child
+                .expect("a positive balance factor implies a left child exists");
+            if self.balance_factor(left) < 0 {
+                self.rotate_left(left);
+            }
+
+            let top = self.rotate_right(handle);
+            self.rebalance_at(handle);
+            Node::recompute_subtree_size(&mut self.storage, top);
+            Node::recompute_height(&mut self.storage, top);
+            return self.rebalance_at(top);
+        } else if bf <= -2 {
+            let right = Node::view(&self.storage, handle).right_child
+                .expect("a negative balance factor implies a right child exists");
+            if self.balance_factor(right) > 0 {
+                self.rotate_right(right);
+            }
+
+            let top = self.rotate_left(handle);
+            self.rebalance_at(handle);
+            Node::recompute_subtree_size(&mut self.storage, top);
+            Node::recompute_height(&mut self.storage, top);
+            return self.rebalance_at(top);
         } else {
-            println!("There is no root!");
+            return handle;
+        }
+    }
+
+    // Repoints `old_child`'s parent slot to `new_child` (or `self.root` if
+    // there was no parent). Left untouched if neither slot holds
+    // `old_child`, which happens at a bounded `retrace`'s boundary.
+    fn attach_child(&mut self, parent: Option<Handle>, old_child: Handle, new_child: Handle) {
+        match parent {
+            Some(parent_handle) => {
+                let parent_node = Node::modify(&mut self.storage, parent_handle);
+                if parent_node.left_child == Some(old_child) {
+                    parent_node.left_child = Some(new_child);
+                } else if parent_node.right_child == Some(old_child) {
+                    parent_node.right_child = Some(new_child);
+                }
+            },
+            None => {
+                self.root = Some(new_child);
+            }
         }
     }
+
+    fn rotate_left(&mut self, x: Handle) -> Handle {
+        let y = Node::view(&self.storage, x).right_child.expect("rotate_left requires a right child");
+        let y_left = Node::view(&self.storage, y).left_child;
+
+        Node::modify(&mut self.storage, x).right_child = y_left;
+        if let Some(y_left) = y_left {
+            Node::modify(&mut self.storage, y_left).parent = Some(x);
+        }
+
+        let x_parent = Node::view(&self.storage, x).parent;
+        Node::modify(&mut self.storage, y).left_child = Some(x);
+        Node::modify(&mut self.storage, x).parent = Some(y);
+        Node::modify(&mut self.storage, y).parent = x_parent;
+        self.attach_child(x_parent, x, y);
+
+        Node::recompute_subtree_size(&mut self.storage, x);
+        Node::recompute_height(&mut self.storage, x);
+        Node::recompute_subtree_size(&mut self.storage, y);
+        Node::recompute_height(&mut self.storage, y);
+
+        return y;
+    }
+
+    fn rotate_right(&mut self, x: Handle) -> Handle {
+        let y = Node::view(&self.storage, x).left_child.expect("rotate_right requires a left child");
+        let y_right = Node::view(&self.storage, y).right_child;
+
+        Node::modify(&mut self.storage, x).left_child = y_right;
+        if let Some(y_right) = y_right {
+            Node::modify(&mut self.storage, y_right).parent = Some(x);
+        }
+
+        let x_parent = Node::view(&self.storage, x).parent;
+        Node::modify(&mut self.storage, y).right_child = Some(x);
+        Node::modify(&mut self.storage, x).parent = Some(y);
+        Node::modify(&mut self.storage, y).parent = x_parent;
+        self.attach_child(x_parent, x, y);
+
+        Node::recompute_subtree_size(&mut self.storage, x);
+        Node::recompute_height(&mut self.storage, x);
+        Node::recompute_subtree_size(&mut self.storage, y);
+        Node::recompute_height(&mut self.storage, y);
+
+        return y;
+    }
+}
+
+impl<'g, Key: PartialOrd + Display + Clone, Payload: Display + Clone + TreeItem<Key, Payload>> BinarySearchTree<Key, Payload> {
+    /// Inserts `query`'s `TreeItem::children()` under their own keys. Does
+    /// nothing if `query` isn't found.
+    pub fn expand<Query>(&mut self, query: &Query)
+    where
+        Key: std::borrow::Borrow<Query> + PartialOrd<Query>,
+        Query: PartialOrd<Key> + ?Sized
+    {
+        if let Some(handle) = self.find_handle(query) {
+            let children = Node::view(&self.storage, handle).content.payload.children();
+            for (child_key, child_payload) in children {
+                self.insert(child_key, child_payload);
+            }
+        }
+    }
+
+    /// Walks the tree in order like `iter`, but skips any element whose
+    /// payload fails `TreeItem::filter(needle)`.
+    pub fn filtered_iter(&'g self, needle: &str) -> impl Iterator<Item = &'g Content<Key, Payload>> + 'g {
+        let needle = needle.to_string();
+        return self.iter().filter(move |content| content.payload.filter(&needle));
+    }
 }
 
 pub struct BSTIterator<'g, Key: PartialOrd + Display, Payload> {
     storage: &'g Arena<Node<Key, Payload>>,
-    node: Option<usize>
+    node: Option<Handle>,
+    // The last node this iterator should yield, set by `range`.
+    end: Option<Handle>
 }
 
 impl<'g, Key: PartialOrd + Display, Payload> BSTIterator<'g, Key, Payload> {
+    /// Returns the element this iterator is positioned on, or `None` if
+    /// exhausted or removed from the tree since.
     pub fn view(&self) -> Option<&'g Content<Key, Payload>> {
-        if let Some(node_index) = self.node {
-            return Some(&self.storage.view(node_index).content);
+        if let Some(handle) = self.node {
+            return self.storage.get(handle).map(|node| &node.content);
         } else {
             return None;
         }
@@ -315,13 +721,16 @@ impl<'g, Key: PartialOrd + Display, Payload> BSTIterator<'g, Key, Payload> {
 impl<'g, Key: PartialOrd + Display, Payload> Iterator for BSTIterator<'g, Key, Payload> {
     type Item = &'g Content<Key, Payload>;
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(current_node_index) = self.node {
-            let current_node = self.storage.view(current_node_index);
+        if let Some(current_handle) = self.node {
+            // If this node was removed since, there's no successor to find.
+            let current_node = self.storage.get(current_handle)?;
             let result = &current_node.content;
-            if let Some(right) = current_node.right_child {
+            if self.end == Some(current_handle) {
+                self.node = None;
+            } else if let Some(right) = current_node.right_child {
                 self.node = Some(Node::fall_min(&self.storage, right));
             } else {
-                self.node = Node::climb(&self.storage, current_node_index);
+                self.node = Node::climb(&self.storage, current_handle);
             }
 
             return Some(result);
@@ -339,6 +748,7 @@ pub struct InsertionResult<'g, Key: PartialOrd + Display, Payload> {
 #[cfg(test)]
 mod tests {
     use crate::BinarySearchTree;
+    use crate::TreeItem;
 
     #[test]
     fn it_works() {
@@ -371,4 +781,187 @@ mod tests {
             println!("key: {} | value: {}", n.key, n.payload);
         }
     }
+
+    #[test]
+    fn rank_and_select() {
+        let mut tree = BinarySearchTree::<i32, i32>::new();
+        let keys = [50, 30, 70, 20, 40, 60, 80];
+        for key in keys {
+            tree.insert(key, key);
+        }
+
+        let mut sorted = keys.to_vec();
+        sorted.sort();
+        for (expected_rank, key) in sorted.iter().enumerate() {
+            assert_eq!(tree.rank(key), Some(expected_rank));
+            assert_eq!(tree.select(expected_rank).map(|c| c.key), Some(*key));
+        }
+
+        assert_eq!(tree.rank(&999), None);
+        assert!(tree.select(sorted.len()).is_none());
+
+        tree.remove(&40);
+        sorted.retain(|k| *k != 40);
+        for (expected_rank, key) in sorted.iter().enumerate() {
+            assert_eq!(tree.rank(key), Some(expected_rank));
+            assert_eq!(tree.select(expected_rank).map(|c| c.key), Some(*key));
+        }
+    }
+
+    #[test]
+    fn balanced_insertion_keeps_logarithmic_height() {
+        let mut tree = BinarySearchTree::<i32, i32>::new_balanced();
+        let count = 1000;
+        for key in 0..count {
+            assert!(tree.insert(key, key).inserted);
+        }
+
+        assert_eq!(tree.iter().count(), count as usize);
+        for (expected_rank, key) in (0..count).enumerate() {
+            assert_eq!(tree.rank(&key), Some(expected_rank));
+        }
+
+        // A degenerate (unbalanced) tree built from sorted input would have
+        // height == count; AVL's invariant keeps it within ~1.44*log2(n).
+        let height = tree.height();
+        assert!(height < 2 * (count as f64).log2() as i32, "height was {}", height);
+
+        for key in (0..count).step_by(3) {
+            tree.remove(&key);
+        }
+        assert_eq!(tree.iter().count(), (count - count / 3 - 1) as usize);
+        assert!(tree.height() < 2 * (count as f64).log2() as i32);
+    }
+
+    #[test]
+    fn range_iteration() {
+        let mut tree = BinarySearchTree::<i32, i32>::new_balanced();
+        for key in 0..50 {
+            tree.insert(key, key);
+        }
+
+        let collect = |r: std::ops::Range<i32>| -> Vec<i32> {
+            tree.range(r).map(|c| c.key).collect()
+        };
+        assert_eq!(collect(10..20), (10..20).collect::<Vec<_>>());
+        assert_eq!(
+            tree.range(10..=20).map(|c| c.key).collect::<Vec<_>>(),
+            (10..=20).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            tree.range(..5).map(|c| c.key).collect::<Vec<_>>(),
+            (0..5).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            tree.range(45..).map(|c| c.key).collect::<Vec<_>>(),
+            (45..50).collect::<Vec<_>>()
+        );
+        assert!(tree.range(100..200).next().is_none());
+        assert!(tree.range(5..5).next().is_none());
+        assert_eq!(tree.range(..).count(), 50);
+    }
+
+    #[test]
+    fn try_insert_reuses_removed_slots_in_place() {
+        let mut tree = BinarySearchTree::<i32, i32>::new();
+        assert!(tree.try_insert(1, 1).unwrap().inserted);
+        assert!(tree.try_insert(2, 2).unwrap().inserted);
+        tree.remove(&1);
+        assert!(tree.try_insert(3, 3).unwrap().inserted);
+
+        let keys: Vec<i32> = tree.iter().map(|c| c.key).collect();
+        assert_eq!(keys, vec![2, 3]);
+
+        let repeat = tree.try_insert(3, 3).unwrap();
+        assert!(!repeat.inserted);
+    }
+
+    #[test]
+    fn arena_handles_become_invalid_after_removal() {
+        let mut arena = crate::arena::Arena::<i32>::new();
+        let first = arena.try_alloc(1).unwrap();
+        assert_eq!(arena.get(first), Some(&1));
+
+        arena.remove(first);
+        assert!(arena.get(first).is_none());
+
+        // The freed slot gets reused, but the new handle carries a bumped
+        // generation, so the stale `first` handle still can't alias it.
+        let second = arena.try_alloc(2).unwrap();
+        assert!(arena.get(first).is_none());
+        assert_eq!(arena.get(second), Some(&2));
+    }
+
+    #[test]
+    fn snapshot_is_independent_of_later_mutation() {
+        let mut tree = BinarySearchTree::<i32, i32>::new_balanced();
+        for key in 0..20 {
+            tree.insert(key, key);
+        }
+
+        let before = tree.snapshot();
+        tree.insert(100, 100);
+        tree.remove(&5);
+
+        assert_eq!(before.iter().map(|c| c.key).collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+        let after: Vec<i32> = tree.iter().map(|c| c.key).collect();
+        assert!(after.contains(&100));
+        assert!(!after.contains(&5));
+
+        // A later snapshot shouldn't see a mutation that comes after it either.
+        let later = tree.snapshot();
+        tree.insert(200, 200);
+        assert!(!later.iter().any(|c| c.key == 200));
+    }
+
+    #[derive(Clone)]
+    struct DirEntry {
+        children: Vec<&'static str>
+    }
+
+    impl std::fmt::Display for DirEntry {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "{} children", self.children.len())
+        }
+    }
+
+    impl TreeItem<String, DirEntry> for DirEntry {
+        fn children(&self) -> Vec<(String, DirEntry)> {
+            return self.children.iter()
+                .map(|name| (name.to_string(), DirEntry{ children: Vec::new() }))
+                .collect();
+        }
+
+        fn filter(&self, needle: &str) -> bool {
+            return self.children.iter().any(|name| name.contains(needle));
+        }
+    }
+
+    #[test]
+    fn expand_materializes_children_lazily() {
+        let mut tree = BinarySearchTree::<String, DirEntry>::new();
+        tree.insert(String::from("root"), DirEntry{ children: vec!["a", "b"] });
+
+        // Nothing below "root" exists yet; expand pulls it in on demand.
+        assert_eq!(tree.iter().count(), 1);
+        tree.expand(&String::from("root"));
+        assert_eq!(tree.iter().map(|c| c.key.clone()).collect::<Vec<_>>(), vec![
+            String::from("a"), String::from("b"), String::from("root")
+        ]);
+
+        // Expanding a key that isn't in the tree does nothing.
+        tree.expand(&String::from("missing"));
+        assert_eq!(tree.iter().count(), 3);
+    }
+
+    #[test]
+    fn filtered_iter_skips_non_matching_payloads() {
+        let mut tree = BinarySearchTree::<String, DirEntry>::new();
+        tree.insert(String::from("docs"), DirEntry{ children: vec!["readme.md"] });
+        tree.insert(String::from("src"), DirEntry{ children: vec!["lib.rs", "arena.rs"] });
+        tree.insert(String::from("empty"), DirEntry{ children: Vec::new() });
+
+        let matches: Vec<String> = tree.filtered_iter("rs").map(|c| c.key.clone()).collect();
+        assert_eq!(matches, vec![String::from("src")]);
+    }
 }